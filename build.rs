@@ -0,0 +1,28 @@
+//! Stamps the resolved `monistode-assemblers` version into the build so
+//! `cache::cache_key` can invalidate cache entries when the assembler's
+//! behavior changes, not just when this crate's own version changes.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let lock = fs::read_to_string("Cargo.lock").expect("Cargo.lock must exist to build");
+    let value: toml::Value = toml::from_str(&lock).expect("Cargo.lock must be valid TOML");
+
+    let version = value
+        .get("package")
+        .and_then(|packages| packages.as_array())
+        .and_then(|packages| {
+            packages.iter().find_map(|package| {
+                if package.get("name")?.as_str()? == "monistode-assemblers" {
+                    package.get("version")?.as_str().map(str::to_string)
+                } else {
+                    None
+                }
+            })
+        })
+        .expect("monistode-assemblers not found in Cargo.lock");
+
+    println!("cargo:rustc-env=MONISTODE_ASSEMBLERS_VERSION={}", version);
+}
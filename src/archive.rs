@@ -0,0 +1,321 @@
+//! Archive format bundling several object files into one distributable
+//! library, following the `ar`/hpk pack-a-directory workflow: `pack`
+//! collects members, `unpack` extracts them, and `link` can pull members
+//! straight out of an archive instead of requiring loose `.o` files.
+
+use monistode_binutils::{ObjectFile, Serializable};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"MAR1";
+
+/// One member of an archive: the path it was packed from (extension
+/// stripped, separators normalized to `/`, so members packed from
+/// different directories don't collide on a bare file stem), its
+/// serialized `ObjectFile` bytes, and the symbol index `pack` computed
+/// for it so `select_members` can decide whether it's needed without
+/// re-deserializing every member.
+pub struct Member {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    pub defined_symbols: Vec<String>,
+    pub referenced_symbols: Vec<String>,
+}
+
+/// A bundle of object files distributed as a single archive, each carrying
+/// the symbol index it was packed with.
+pub struct Archive {
+    pub members: Vec<Member>,
+}
+
+/// The symbols an object file defines and the symbols its relocations
+/// reference, used both to build an archive member's index and to track
+/// a link's running set of defined/undefined symbols.
+pub fn symbol_index(object_file: &ObjectFile) -> (Vec<String>, Vec<String>) {
+    let mut defined = Vec::new();
+    let mut referenced = Vec::new();
+    for section in object_file.clone().sections() {
+        defined.extend(section.symbols().into_iter().map(|symbol| symbol.name));
+        referenced.extend(
+            section
+                .relocations()
+                .into_iter()
+                .map(|relocation| relocation.symbol),
+        );
+    }
+    (defined, referenced)
+}
+
+fn member_name(path: &Path) -> String {
+    path.with_extension("")
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+impl Archive {
+    /// Collect object files from explicit paths into an archive, validating
+    /// that each one deserializes before it's bundled.
+    pub fn pack(paths: &[PathBuf]) -> Result<Archive, String> {
+        let mut members = Vec::with_capacity(paths.len());
+        for path in paths {
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            let (_, object_file) = ObjectFile::deserialize(&bytes)
+                .map_err(|e| format!("{:?} is not a valid object file: {:?}", path, e))?;
+
+            let (defined_symbols, referenced_symbols) = symbol_index(&object_file);
+            members.push(Member {
+                name: member_name(path),
+                bytes,
+                defined_symbols,
+                referenced_symbols,
+            });
+        }
+        Ok(Archive { members })
+    }
+
+    /// Collect every `.o` file directly inside `dir` into an archive.
+    pub fn pack_dir(dir: &Path) -> Result<Archive, String> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("o"))
+            .collect();
+        paths.sort();
+        Self::pack(&paths)
+    }
+
+    /// Serialize the archive to its on-disk representation.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&(self.members.len() as u32).to_le_bytes());
+        for member in &self.members {
+            write_string(&mut out, &member.name);
+            write_string_list(&mut out, &member.defined_symbols);
+            write_string_list(&mut out, &member.referenced_symbols);
+            out.extend_from_slice(&(member.bytes.len() as u64).to_le_bytes());
+            out.extend_from_slice(&member.bytes);
+        }
+        out
+    }
+
+    /// Parse an archive from its on-disk representation.
+    ///
+    /// Every length read from `bytes` is checked against what's actually
+    /// left before it's used to slice, so a truncated or corrupt archive
+    /// is reported as an error instead of panicking.
+    pub fn deserialize(bytes: &[u8]) -> Result<Archive, String> {
+        let mut reader = Reader::new(bytes);
+
+        let magic = reader.take(4)?;
+        if magic != MAGIC {
+            return Err("Not a monistode archive".to_string());
+        }
+        let count = reader.take_u32()? as usize;
+        let mut members = Vec::new();
+
+        for _ in 0..count {
+            let name = reader.take_string()?;
+            let defined_symbols = reader.take_string_list()?;
+            let referenced_symbols = reader.take_string_list()?;
+            let data_len = reader.take_u64()? as usize;
+            let bytes = reader.take(data_len)?.to_vec();
+
+            members.push(Member {
+                name,
+                bytes,
+                defined_symbols,
+                referenced_symbols,
+            });
+        }
+
+        Ok(Archive { members })
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_string_list(out: &mut Vec<u8>, items: &[String]) {
+    out.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    for item in items {
+        write_string(out, item);
+    }
+}
+
+/// A cursor over archive bytes that turns "read past the end" into an
+/// error instead of a panicking slice index.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .ok_or_else(|| "Corrupt archive: length overflow".to_string())?;
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or_else(|| "Corrupt archive: unexpected end of data".to_string())?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn take_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn take_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn take_string(&mut self) -> Result<String, String> {
+        let len = self.take_u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| format!("Corrupt archive member name: {}", e))
+    }
+
+    fn take_string_list(&mut self) -> Result<Vec<String>, String> {
+        let count = self.take_u32()? as usize;
+        (0..count).map(|_| self.take_string()).collect()
+    }
+}
+
+/// Select archive members needed to resolve `pending_undefined`.
+///
+/// Repeatedly pulls in any not-yet-selected member whose defined symbols
+/// intersect `pending_undefined`, folding the member's own defined symbols
+/// into `defined` and its referenced symbols into `pending_undefined` (so
+/// a selected member can pull in further members to satisfy its own
+/// references), until a full pass over the archive selects nothing new —
+/// a fixpoint over the members transitively required, mirroring how a
+/// real linker resolves an archive against the objects seen so far.
+pub fn select_members<'a>(
+    archive: &'a Archive,
+    pending_undefined: &mut HashSet<String>,
+    defined: &mut HashSet<String>,
+) -> Vec<&'a Member> {
+    let mut selected = Vec::new();
+    let mut remaining: Vec<&Member> = archive.members.iter().collect();
+
+    loop {
+        pending_undefined.retain(|symbol| !defined.contains(symbol));
+
+        let mut progressed = false;
+        remaining.retain(|member| {
+            let needed = member
+                .defined_symbols
+                .iter()
+                .any(|symbol| pending_undefined.contains(symbol));
+            if !needed {
+                return true;
+            }
+            defined.extend(member.defined_symbols.iter().cloned());
+            pending_undefined.extend(member.referenced_symbols.iter().cloned());
+            selected.push(*member);
+            progressed = true;
+            false
+        });
+
+        if !progressed {
+            break;
+        }
+    }
+
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, defined: &[&str], referenced: &[&str]) -> Member {
+        Member {
+            name: name.to_string(),
+            bytes: vec![1, 2, 3],
+            defined_symbols: defined.iter().map(|s| s.to_string()).collect(),
+            referenced_symbols: referenced.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_serialize_deserialize() {
+        let archive = Archive {
+            members: vec![
+                member("a/foo", &["main"], &["helper"]),
+                member("b/foo", &["helper"], &[]),
+            ],
+        };
+
+        let bytes = archive.serialize();
+        let parsed = Archive::deserialize(&bytes).expect("valid archive");
+
+        assert_eq!(parsed.members.len(), 2);
+        assert_eq!(parsed.members[0].name, "a/foo");
+        assert_eq!(parsed.members[0].defined_symbols, vec!["main"]);
+        assert_eq!(parsed.members[0].referenced_symbols, vec!["helper"]);
+        assert_eq!(parsed.members[1].name, "b/foo");
+        assert_eq!(parsed.members[1].bytes, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn deserialize_rejects_bad_magic() {
+        assert!(Archive::deserialize(b"NOPE0000").is_err());
+    }
+
+    #[test]
+    fn deserialize_reports_truncated_data_instead_of_panicking() {
+        let archive = Archive {
+            members: vec![member("foo", &["main"], &[])],
+        };
+        let bytes = archive.serialize();
+        // Cut the archive off partway through the last member's payload.
+        let truncated = &bytes[..bytes.len() - 1];
+
+        assert!(Archive::deserialize(truncated).is_err());
+    }
+
+    #[test]
+    fn select_members_follows_transitive_references_to_a_fixpoint() {
+        let archive = Archive {
+            members: vec![
+                member("a", &["a_fn"], &["b_fn"]),
+                member("b", &["b_fn"], &["c_fn"]),
+                member("c", &["c_fn"], &[]),
+                member("unrelated", &["d_fn"], &[]),
+            ],
+        };
+
+        let mut pending_undefined: HashSet<String> = ["a_fn".to_string()].into_iter().collect();
+        let mut defined: HashSet<String> = HashSet::new();
+
+        let selected = select_members(&archive, &mut pending_undefined, &mut defined);
+        let names: HashSet<&str> = selected.iter().map(|m| m.name.as_str()).collect();
+
+        assert_eq!(names, ["a", "b", "c"].into_iter().collect());
+    }
+
+    #[test]
+    fn select_members_skips_archive_members_nothing_needs() {
+        let archive = Archive {
+            members: vec![member("only", &["only_fn"], &[])],
+        };
+
+        let mut pending_undefined: HashSet<String> = HashSet::new();
+        let mut defined: HashSet<String> = HashSet::new();
+
+        let selected = select_members(&archive, &mut pending_undefined, &mut defined);
+        assert!(selected.is_empty());
+    }
+}
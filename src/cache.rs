@@ -0,0 +1,101 @@
+//! Content-addressed cache for assembled object files, modeled on sccache:
+//! the cache key is a digest over the normalized inputs (source bytes,
+//! target, assembler version), and hits skip re-parsing entirely.
+
+use monistode_binutils::{ObjectFile, Serializable};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the environment variable that provides a default cache directory
+/// when `--cache-dir` is not passed explicitly.
+pub const CACHE_DIR_ENV: &str = "MONISTODE_CACHE";
+
+/// Compute the cache key for a given assembly input.
+///
+/// The key is a SHA-256 hex digest over the source bytes, the target
+/// string, this crate's version, and the resolved `monistode-assemblers`
+/// version (stamped in by `build.rs` from `Cargo.lock`, since that's the
+/// crate that actually does the parsing), so a change to any of them
+/// invalidates the entry.
+pub fn cache_key(source: &[u8], target: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(source);
+    hasher.update(b"\0");
+    hasher.update(target.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(b"\0");
+    hasher.update(env!("MONISTODE_ASSEMBLERS_VERSION").as_bytes());
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Path to the cache entry for `key` under `cache_dir`, sharded by the
+/// first two hex characters to keep directory listings small.
+fn entry_path(cache_dir: &Path, key: &str) -> PathBuf {
+    cache_dir.join(&key[0..2]).join(key)
+}
+
+/// Look up `key` in `cache_dir`, returning the cached object file on a hit.
+///
+/// The stored bytes are verified to round-trip through
+/// `ObjectFile::deserialize` before being trusted, so a corrupt entry is
+/// treated as a miss rather than a hard error.
+pub fn load(cache_dir: &Path, key: &str) -> Option<ObjectFile> {
+    let path = entry_path(cache_dir, key);
+    let bytes = fs::read(&path).ok()?;
+    match ObjectFile::deserialize(&bytes) {
+        Ok((_, object_file)) => Some(object_file),
+        Err(_) => None,
+    }
+}
+
+/// Store `object_file` under `key` in `cache_dir`.
+///
+/// Writes go to a temporary file beside the final path and are renamed
+/// into place, so a crash mid-write never leaves a partially-written entry
+/// visible to other `as` invocations.
+pub fn store(cache_dir: &Path, key: &str, object_file: &ObjectFile) -> std::io::Result<()> {
+    let path = entry_path(cache_dir, key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, object_file.serialize())?;
+    fs::rename(&tmp_path, &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_deterministic() {
+        assert_eq!(
+            cache_key(b"push 1", "stack"),
+            cache_key(b"push 1", "stack")
+        );
+    }
+
+    #[test]
+    fn cache_key_changes_with_source() {
+        assert_ne!(
+            cache_key(b"push 1", "stack"),
+            cache_key(b"push 2", "stack")
+        );
+    }
+
+    #[test]
+    fn cache_key_changes_with_target() {
+        assert_ne!(
+            cache_key(b"push 1", "stack"),
+            cache_key(b"push 1", "accumulator")
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Support for the one-shot `compile` command: assemble one or more
+//! sources and link them straight into an executable without touching
+//! disk for intermediate object files.
+
+use std::collections::HashMap;
+
+/// A `--config key=value` flag describing how the produced executable
+/// should launch, e.g. an entry point override or initial stack size.
+///
+/// Parsed eagerly so malformed flags are reported before any assembling
+/// happens, but there's currently nowhere to apply them to: checked
+/// against `monistode_binutils` 0.1.4's public API, `Executable` stores
+/// `entry_point` as a private field with only a getter, `Executable::new`
+/// always sets it to 0, and `ExecutableHeader::new` hardcodes it to 0
+/// behind a `// TODO search for start symbol` that was never implemented
+/// — there is no constructor, setter, or header this crate can use to
+/// carry an entry point (or any other run-configuration value) into the
+/// serialized executable. See the warning emitted in `main`.
+pub type RunConfig = HashMap<String, String>;
+
+/// Parse a single `key=value` flag as passed to `--config`.
+pub fn parse_config_entry(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err(format!(
+            "Invalid --config entry (expected key=value): {}",
+            raw
+        )),
+    }
+}
+
+/// Collect repeated `--config key=value` flags into a [`RunConfig`].
+pub fn parse_config(raw: &[String]) -> Result<RunConfig, String> {
+    raw.iter().map(|entry| parse_config_entry(entry)).collect()
+}
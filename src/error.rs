@@ -0,0 +1,186 @@
+//! Structured diagnostics for assembler and linker failures.
+//!
+//! Replaces the old `Result<_, String>` + `eprintln!`-and-return pattern
+//! so failures carry the offending path, the failing stage, and (when
+//! the assembler provides one) a source span to put a caret under —
+//! and so `main` can report one non-zero exit code instead of an opaque
+//! one-liner per command.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// Which stage of the assemble/link pipeline raised a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Read,
+    Parse,
+    Deserialize,
+    Link,
+    Write,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Read => "read",
+            Stage::Parse => "parse",
+            Stage::Deserialize => "deserialize",
+            Stage::Link => "link",
+            Stage::Write => "write",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A 1-based line/column used to draw a caret under the offending span of
+/// a source file.
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A single diagnostic raised while assembling or linking.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub path: PathBuf,
+    pub span: Option<Span>,
+    pub message: String,
+    source: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(stage: Stage, path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            stage,
+            path: path.into(),
+            span: None,
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Attach the source text the span refers to, so `render` can show the
+    /// offending line under the caret instead of just its coordinates.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Render as an aligned, multi-line diagnostic. When a span and its
+    /// source text are both known, the offending line is shown with a
+    /// caret under the exact column; otherwise only the location (if any)
+    /// is reported.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "error[{}]: {}\n  --> {}",
+            self.stage,
+            self.message,
+            self.path.display()
+        );
+
+        let Some(span) = self.span else {
+            return out;
+        };
+        out.push_str(&format!(":{}:{}", span.line, span.column));
+
+        let Some(line_text) = self
+            .source
+            .as_deref()
+            .and_then(|source| source.lines().nth(span.line - 1))
+        else {
+            return out;
+        };
+
+        let gutter = format!("{} | ", span.line);
+        let margin = " ".repeat(gutter.len() - 3);
+        out.push_str(&format!("\n{} |\n{}{}", margin, gutter, line_text));
+        out.push_str(&format!(
+            "\n{} | {}^",
+            margin,
+            " ".repeat(span.column.saturating_sub(1))
+        ));
+        out
+    }
+}
+
+/// Best-effort extraction of a `line N, column N` location from an
+/// assembler error's rendered message.
+///
+/// `monistode_assemblers::stack::parse` doesn't return a structured error
+/// with span fields — only a `String` — but for the common case (trailing
+/// unparsed input) that string is generated from a known position via
+/// `"Unexpected input at line {line}, column {column}"`, so the position
+/// survives in text form. This recovers it rather than discarding it, and
+/// returns `None` (so the diagnostic just omits the caret) for the rarer
+/// case where the underlying `combine` parse error has no such phrase.
+pub fn extract_span(message: &str) -> Option<Span> {
+    let after_line = message.split_once("line ")?.1;
+    let line: usize = after_line
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+
+    let after_column = after_line.split_once("column ")?.1;
+    let column: usize = after_column
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+
+    if line == 0 {
+        return None;
+    }
+
+    Some(Span { line, column })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_without_span_omits_location() {
+        let diagnostic = Diagnostic::new(Stage::Read, "main.asm", "file not found");
+        assert_eq!(
+            diagnostic.render(),
+            "error[read]: file not found\n  --> main.asm"
+        );
+    }
+
+    #[test]
+    fn render_with_span_draws_a_caret_under_the_exact_column() {
+        let diagnostic = Diagnostic::new(Stage::Parse, "main.asm", "unexpected token")
+            .with_source("push 1\npud 2\n")
+            .with_span(Span { line: 2, column: 3 });
+
+        let rendered = diagnostic.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "error[parse]: unexpected token");
+        assert_eq!(lines[1], "  --> main.asm:2:3");
+        assert_eq!(lines[3], "2 | pud 2");
+        // The caret must land under the exact column of the line above,
+        // once the gutter width ("2 | ") is accounted for.
+        assert_eq!(lines[4], "  |   ^");
+    }
+
+    #[test]
+    fn extract_span_parses_the_assembler_error_phrase() {
+        let span = extract_span("Unexpected input at line 3, column 5").unwrap();
+        assert_eq!(span.line, 3);
+        assert_eq!(span.column, 5);
+    }
+
+    #[test]
+    fn extract_span_returns_none_without_a_location_phrase() {
+        assert!(extract_span("some other parse failure").is_none());
+    }
+}
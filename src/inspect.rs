@@ -0,0 +1,244 @@
+//! Support for the `inspect` command: a machine-readable view of an
+//! object file or executable, so editors, test harnesses and CI scripts
+//! can query binutils output instead of scraping human-readable text.
+
+use monistode_binutils::object_file::Section;
+use monistode_binutils::{Executable, ObjectFile, Serializable, Symbol};
+use std::collections::HashSet;
+
+/// Output format for `monistode inspect`.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Pretty,
+}
+
+/// What `inspect` found after deserializing the input bytes.
+pub enum Kind {
+    Object(ObjectFile),
+    Executable(Executable),
+}
+
+/// Try to deserialize `bytes` as an object file, falling back to an
+/// executable, since both share this tool's input position and there's no
+/// on-disk tag distinguishing them.
+pub fn deserialize(bytes: &[u8]) -> Result<Kind, String> {
+    if let Ok((_, object_file)) = ObjectFile::deserialize(bytes) {
+        return Ok(Kind::Object(object_file));
+    }
+    if let Ok((_, executable)) = Executable::deserialize(bytes) {
+        return Ok(Kind::Executable(executable));
+    }
+    Err("Input is neither a valid object file nor a valid executable".to_string())
+}
+
+fn symbol_json(symbol: &Symbol) -> serde_json::Value {
+    serde_json::json!({
+        "name": symbol.name,
+        "address_bits": symbol.address.0,
+    })
+}
+
+/// Render an object file's sections, its flattened symbol table, and any
+/// symbol referenced by a relocation but defined in none of its sections.
+fn object_document(object_file: &ObjectFile, symbols_only: bool) -> serde_json::Value {
+    let sections = object_file.clone().sections();
+
+    let defined: HashSet<&str> = sections
+        .iter()
+        .flat_map(|section| match section {
+            Section::Text(text) => text.symbols.iter().map(|symbol| symbol.name.as_str()),
+        })
+        .collect();
+
+    let all_symbols: Vec<&Symbol> = sections
+        .iter()
+        .flat_map(|section| match section {
+            Section::Text(text) => text.symbols.iter(),
+        })
+        .collect();
+
+    if symbols_only {
+        return serde_json::json!({
+            "symbols": all_symbols.iter().map(|s| symbol_json(s)).collect::<Vec<_>>(),
+        });
+    }
+
+    let section_docs: Vec<serde_json::Value> = sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| match section {
+            Section::Text(text) => serde_json::json!({
+                "index": index,
+                "kind": "text",
+                "size_bytes": text.data.len().div_ceil(8),
+                "symbols": text.symbols.iter().map(symbol_json).collect::<Vec<_>>(),
+                "relocations": text.relocations.iter().map(|relocation| serde_json::json!({
+                    "symbol": relocation.symbol,
+                    "address_bits": relocation.address.0,
+                    "relative": relocation.relative,
+                })).collect::<Vec<_>>(),
+            }),
+        })
+        .collect();
+
+    let unresolved: Vec<&str> = sections
+        .iter()
+        .flat_map(|section| match section {
+            Section::Text(text) => text.relocations.iter().map(|r| r.symbol.as_str()),
+        })
+        .filter(|symbol| !defined.contains(symbol))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    serde_json::json!({
+        "sections": section_docs,
+        "symbols": all_symbols.iter().map(|s| symbol_json(s)).collect::<Vec<_>>(),
+        "unresolved_external_symbols": unresolved,
+    })
+}
+
+/// Render an executable's segments and its flattened symbol table.
+fn executable_document(executable: &Executable, symbols_only: bool) -> serde_json::Value {
+    let all_symbols: Vec<Symbol> = executable
+        .segments()
+        .iter()
+        .flat_map(|segment| segment.symbols())
+        .collect();
+
+    if symbols_only {
+        return serde_json::json!({
+            "symbols": all_symbols.iter().map(symbol_json).collect::<Vec<_>>(),
+        });
+    }
+
+    let segment_docs: Vec<serde_json::Value> = executable
+        .segments()
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            serde_json::json!({
+                "index": index,
+                "address_space_start": segment.address_space_start,
+                "address_space_size": segment.address_space_size,
+                "flags": {
+                    "executable": segment.flags.executable,
+                    "writable": segment.flags.writable,
+                    "readable": segment.flags.readable,
+                    "special": segment.flags.special,
+                },
+                "symbols": segment.symbols().iter().map(symbol_json).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "entry_point": executable.entry_point(),
+        "segments": segment_docs,
+        "symbols": all_symbols.iter().map(symbol_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Render `kind` as the requested format.
+pub fn render(kind: &Kind, format: Format, symbols_only: bool) -> String {
+    let (artifact_type, serialized_len, mut document) = match kind {
+        Kind::Object(object_file) => (
+            "object",
+            object_file.serialize().len(),
+            object_document(object_file, symbols_only),
+        ),
+        Kind::Executable(executable) => (
+            "executable",
+            executable.serialize().len(),
+            executable_document(executable, symbols_only),
+        ),
+    };
+
+    let fields = document.as_object_mut().expect("document is a JSON object");
+    fields.insert("type".to_string(), serde_json::json!(artifact_type));
+    fields.insert(
+        "serialized_len".to_string(),
+        serde_json::json!(serialized_len),
+    );
+
+    match format {
+        Format::Json => document.to_string(),
+        Format::Pretty => serde_json::to_string_pretty(&document).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use monistode_binutils::executable::segments::flags::SegmentFlags;
+    use monistode_binutils::executable::segments::Segment;
+    use monistode_binutils::object_file::relocations::Relocation;
+    use monistode_binutils::object_file::sections::text::TextSection;
+    use monistode_binutils::{Address, Architecture};
+
+    fn object_with_one_undefined_reference() -> ObjectFile {
+        let text = TextSection::new(
+            Default::default(),
+            vec![Symbol {
+                name: "main".to_string(),
+                address: Address(0),
+            }],
+            vec![Relocation {
+                symbol: "undef_fn".to_string(),
+                address: Address(0),
+                relative: false,
+            }],
+        );
+        ObjectFile::with_sections(Architecture::Stack, vec![Section::Text(text)])
+    }
+
+    #[test]
+    fn reports_unresolved_external_symbols() {
+        let object_file = object_with_one_undefined_reference();
+        let rendered = render(&Kind::Object(object_file), Format::Json, false);
+        let document: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(document["type"], "object");
+        assert_eq!(document["unresolved_external_symbols"][0], "undef_fn");
+        assert_eq!(document["symbols"][0]["name"], "main");
+    }
+
+    #[test]
+    fn symbols_only_omits_sections() {
+        let object_file = object_with_one_undefined_reference();
+        let rendered = render(&Kind::Object(object_file), Format::Json, true);
+        let document: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert!(document.get("sections").is_none());
+        assert_eq!(document["symbols"][0]["name"], "main");
+    }
+
+    #[test]
+    fn renders_executable_segments() {
+        let segment = Segment::new(
+            0x1000,
+            1,
+            0,
+            SegmentFlags {
+                executable: true,
+                writable: false,
+                readable: true,
+                special: false,
+            },
+            Default::default(),
+            vec![Symbol {
+                name: "_start".to_string(),
+                address: Address(0),
+            }],
+        );
+        let executable = Executable::new(Architecture::Stack, vec![segment]);
+
+        let rendered = render(&Kind::Executable(executable), Format::Json, false);
+        let document: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(document["type"], "executable");
+        assert_eq!(document["segments"][0]["address_space_start"], 0x1000);
+        assert_eq!(document["symbols"][0]["name"], "_start");
+    }
+}
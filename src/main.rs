@@ -1,9 +1,19 @@
 use clap::{Parser, Subcommand};
 use monistode_assemblers::stack;
 use monistode_binutils::{Executable, ObjectFile, Serializable};
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 
+mod archive;
+mod cache;
+mod compile;
+mod error;
+mod inspect;
+mod manifest;
+
+use error::{Diagnostic, Stage};
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -25,39 +35,148 @@ enum Commands {
         /// Assembly target type
         #[arg(short = 't', long, default_value = "stack")]
         target: String,
+
+        /// Directory to cache assembled object files in, keyed by a digest
+        /// of the source, target and assembler version
+        #[arg(long, value_name = "DIR", env = cache::CACHE_DIR_ENV)]
+        cache_dir: Option<PathBuf>,
+
+        /// Disable the build cache even if `--cache-dir`/MONISTODE_CACHE is set
+        #[arg(long)]
+        no_cache: bool,
     },
 
-    /// Link object files into an executable
+    /// Link object files (and archives) into an executable
     Link {
-        /// Input object files
+        /// Input object files or archives (see `pack`)
         input: Vec<PathBuf>,
 
         /// Output executable file
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
     },
+
+    /// Bundle object files into a single archive
+    Pack {
+        /// Object files to pack; if omitted, every `.o` file in `--dir` is packed
+        input: Vec<PathBuf>,
+
+        /// Directory to collect `.o` files from instead of an explicit list
+        #[arg(long, value_name = "DIR", conflicts_with = "input")]
+        dir: Option<PathBuf>,
+
+        /// Output archive file
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+
+    /// Extract the object files bundled in an archive
+    Unpack {
+        /// Archive to extract
+        input: PathBuf,
+
+        /// Directory to write the extracted `<name>.o` files to
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Assemble and link a multi-file program described by a build manifest
+    Build {
+        /// Name of the output to build; defaults to the manifest's `default` output
+        target: Option<String>,
+
+        /// Path to the build manifest (TOML or RON)
+        #[arg(short, long, value_name = "FILE", default_value = "monistode.toml")]
+        manifest: PathBuf,
+    },
+
+    /// Assemble and link source files into an executable in one step
+    Compile {
+        /// Input assembly files
+        input: Vec<PathBuf>,
+
+        /// Output executable file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Assembly target type
+        #[arg(short = 't', long, default_value = "stack")]
+        target: String,
+
+        /// Also write each intermediate object file next to its source
+        #[arg(long)]
+        emit_obj: bool,
+
+        /// Run-configuration flag, e.g.
+        /// `--config entry=main --config stack-size=4096`; checked for
+        /// `key=value` form but not embedded in the executable
+        #[arg(long = "config", value_name = "KEY=VALUE")]
+        config: Vec<String>,
+    },
+
+    /// Print a structured description of an object file or executable
+    Inspect {
+        /// Object file or executable to inspect
+        input: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: inspect::Format,
+
+        /// Only include the symbol table in the output
+        #[arg(long)]
+        symbols_only: bool,
+    },
 }
 
-fn assemble_file(input_path: &PathBuf, target: &str) -> Result<ObjectFile, String> {
+fn assemble_file(input_path: &PathBuf, target: &str) -> Result<ObjectFile, Diagnostic> {
     // Read input file
     let input =
-        fs::read_to_string(input_path).map_err(|e| format!("Failed to read input file: {}", e))?;
+        fs::read_to_string(input_path).map_err(|e| Diagnostic::new(Stage::Read, input_path, e))?;
 
     // Parse based on target
     match target {
-        "stack" => stack::parse(&input).map_err(|e| format!("{}", e)),
-        _ => Err(format!("Unsupported target type: {}", target)),
+        "stack" => stack::parse(&input).map_err(|e| {
+            // `e` already embeds the offending line and a caret under it
+            // (monistode_assemblers renders those itself), so only the
+            // first line is the actual message — the rest would duplicate
+            // what Diagnostic::render draws from `with_source`/`with_span`.
+            let message = e.lines().next().unwrap_or(&e);
+            let diagnostic =
+                Diagnostic::new(Stage::Parse, input_path, message).with_source(input.clone());
+            match error::extract_span(&e) {
+                Some(span) => diagnostic.with_span(span),
+                None => diagnostic,
+            }
+        }),
+        _ => Err(Diagnostic::new(
+            Stage::Parse,
+            input_path,
+            format!("Unsupported target type: {}", target),
+        )),
     }
 }
 
-fn main() {
+fn main() -> std::process::ExitCode {
     let cli = Cli::parse();
 
-    match &cli.command {
+    match run(&cli.command) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(diagnostic) => {
+            eprintln!("{}", diagnostic.render());
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: &Commands) -> Result<(), Diagnostic> {
+    match command {
         Commands::As {
             input,
             output,
             target,
+            cache_dir,
+            no_cache,
         } => {
             // Determine output path
             let output_path = output.clone().unwrap_or_else(|| {
@@ -66,23 +185,48 @@ fn main() {
                 path
             });
 
-            // Assemble the file
-            match assemble_file(input, target) {
-                Ok(object_file) => {
-                    // Serialize the object file
-                    let serialized = object_file.serialize();
-
-                    // Write to output file
-                    match fs::write(&output_path, &serialized) {
-                        Ok(_) => println!("Successfully wrote object file to {:?}", output_path),
-                        Err(e) => eprintln!("Failed to write output file: {}", e),
+            // Resolve the object file, consulting the cache when enabled
+            let cache_dir = (!no_cache).then(|| cache_dir.clone()).flatten();
+            let object_file = match &cache_dir {
+                Some(cache_dir) => {
+                    let source_bytes =
+                        fs::read(input).map_err(|e| Diagnostic::new(Stage::Read, input, e))?;
+                    let key = cache::cache_key(&source_bytes, target);
+                    match cache::load(cache_dir, &key) {
+                        Some(object_file) => {
+                            println!("cache hit for {:?}", input);
+                            object_file
+                        }
+                        None => {
+                            println!("cache miss for {:?}", input);
+                            let object_file = assemble_file(input, target)?;
+                            if let Err(e) = cache::store(cache_dir, &key, &object_file) {
+                                eprintln!("Failed to write cache entry: {}", e);
+                            }
+                            object_file
+                        }
                     }
                 }
-                Err(e) => eprintln!("{}", e),
-            }
+                None => assemble_file(input, target)?,
+            };
+
+            // Serialize and write the object file
+            let serialized = object_file.serialize();
+            fs::write(&output_path, &serialized)
+                .map_err(|e| Diagnostic::new(Stage::Write, &output_path, e))?;
+            println!("Successfully wrote object file to {:?}", output_path);
+            Ok(())
         }
 
         Commands::Link { input, output } => {
+            if input.is_empty() {
+                return Err(Diagnostic::new(
+                    Stage::Read,
+                    PathBuf::new(),
+                    "No input files provided",
+                ));
+            }
+
             // Determine output path
             let output_path = output.clone().unwrap_or_else(|| {
                 let mut path = input[0].clone();
@@ -90,46 +234,278 @@ fn main() {
                 path
             });
 
-            // Read and merge all input object files
+            // Read and merge all input object files (and archives),
+            // tracking the running set of defined/undefined symbols so
+            // each archive only contributes the members that are actually
+            // needed (see `archive::select_members`).
             let mut merged_object: Option<ObjectFile> = None;
+            let mut defined: HashSet<String> = HashSet::new();
+            let mut pending_undefined: HashSet<String> = HashSet::new();
 
             for path in input {
-                match fs::read(path) {
-                    Ok(bytes) => match ObjectFile::deserialize(&bytes) {
-                        Ok((_, object_file)) => match merged_object.take() {
-                            Some(mut existing) => {
-                                existing.merge(object_file);
-                                merged_object = Some(existing);
-                            }
-                            None => merged_object = Some(object_file),
-                        },
-                        Err(e) => {
-                            eprintln!("Failed to deserialize object file {:?}: {:?}", path, e);
-                            return;
+                let bytes = fs::read(path).map_err(|e| Diagnostic::new(Stage::Read, path, e))?;
+
+                let object_files: Vec<ObjectFile> =
+                    if let Ok(archive) = archive::Archive::deserialize(&bytes) {
+                        let selected = archive::select_members(
+                            &archive,
+                            &mut pending_undefined,
+                            &mut defined,
+                        );
+                        println!(
+                            "Including {} of {} member(s) of archive {:?}",
+                            selected.len(),
+                            archive.members.len(),
+                            path
+                        );
+                        selected
+                            .into_iter()
+                            .map(|member| {
+                                ObjectFile::deserialize(&member.bytes)
+                                    .map(|(_, object_file)| object_file)
+                                    .map_err(|e| {
+                                        Diagnostic::new(
+                                            Stage::Deserialize,
+                                            path,
+                                            format!("archive member {:?}: {:?}", member.name, e),
+                                        )
+                                    })
+                            })
+                            .collect::<Result<Vec<_>, _>>()?
+                    } else {
+                        let (_, object_file) = ObjectFile::deserialize(&bytes).map_err(|e| {
+                            Diagnostic::new(Stage::Deserialize, path, format!("{:?}", e))
+                        })?;
+                        let (object_defined, object_referenced) = archive::symbol_index(&object_file);
+                        defined.extend(object_defined);
+                        pending_undefined.extend(object_referenced);
+                        vec![object_file]
+                    };
+
+                for object_file in object_files {
+                    match merged_object.take() {
+                        Some(mut existing) => {
+                            existing.merge(object_file);
+                            merged_object = Some(existing);
                         }
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to read object file {:?}: {}", path, e);
-                        return;
+                        None => merged_object = Some(object_file),
                     }
                 }
             }
 
             // Link the merged object file
-            if let Some(object_file) = merged_object {
-                match Executable::try_from(object_file) {
-                    Ok(executable) => {
-                        let serialized = executable.serialize();
-                        match fs::write(&output_path, &serialized) {
-                            Ok(_) => println!("Successfully wrote executable to {:?}", output_path),
-                            Err(e) => eprintln!("Failed to write executable: {}", e),
-                        }
+            let object_file = merged_object.ok_or_else(|| {
+                Diagnostic::new(Stage::Link, &output_path, "No input files provided")
+            })?;
+            link_and_write(object_file, &output_path)
+        }
+
+        Commands::Build { target, manifest } => {
+            let project =
+                manifest::load(manifest).map_err(|e| Diagnostic::new(Stage::Read, manifest, e))?;
+
+            let (target_name, output_def) =
+                manifest::resolve_target(&project, target.as_deref())
+                    .map_err(|e| Diagnostic::new(Stage::Link, manifest, e))?;
+
+            // Assemble each source and merge the resulting object files,
+            // exactly as the `Link` arm does for explicit object inputs.
+            let mut merged_object: Option<ObjectFile> = None;
+
+            for source_name in &output_def.sources {
+                let source_def = project.sources.get(source_name).ok_or_else(|| {
+                    Diagnostic::new(
+                        Stage::Link,
+                        manifest,
+                        format!("No such source: {}", source_name),
+                    )
+                })?;
+
+                let object_file = assemble_file(&source_def.path, &source_def.target)?;
+                match merged_object.take() {
+                    Some(mut existing) => {
+                        existing.merge(object_file);
+                        merged_object = Some(existing);
+                    }
+                    None => merged_object = Some(object_file),
+                }
+            }
+
+            // Link the merged object file
+            let output_path = output_def
+                .output
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(target_name).with_extension("x"));
+            let object_file = merged_object.ok_or_else(|| {
+                Diagnostic::new(
+                    Stage::Link,
+                    manifest,
+                    format!("No sources declared for target: {}", target_name),
+                )
+            })?;
+            link_and_write(object_file, &output_path)
+        }
+
+        Commands::Compile {
+            input,
+            output,
+            target,
+            emit_obj,
+            config,
+        } => {
+            if input.is_empty() {
+                return Err(Diagnostic::new(
+                    Stage::Read,
+                    PathBuf::new(),
+                    "No input files provided",
+                ));
+            }
+
+            let run_config = compile::parse_config(config)
+                .map_err(|e| Diagnostic::new(Stage::Parse, &input[0], e))?;
+            if !run_config.is_empty() {
+                eprintln!(
+                    "Warning: --config is parsed but not embedded in the executable; \
+                     monistode_binutils::Executable has no constructor or setter that accepts \
+                     an entry point (or any other run-configuration value) to carry"
+                );
+            }
+
+            // Determine output path
+            let output_path = output.clone().unwrap_or_else(|| {
+                let mut path = input[0].clone();
+                path.set_extension("x");
+                path
+            });
+
+            // Assemble and merge every source, keeping object files in
+            // memory instead of round-tripping them through disk
+            let mut merged_object: Option<ObjectFile> = None;
+
+            for path in input {
+                let object_file = assemble_file(path, target)?;
+
+                if *emit_obj {
+                    let mut obj_path = path.clone();
+                    obj_path.set_extension("o");
+                    fs::write(&obj_path, object_file.serialize())
+                        .map_err(|e| Diagnostic::new(Stage::Write, &obj_path, e))?;
+                }
+
+                match merged_object.take() {
+                    Some(mut existing) => {
+                        existing.merge(object_file);
+                        merged_object = Some(existing);
                     }
-                    Err(e) => eprintln!("Linking failed: {:?}", e),
+                    None => merged_object = Some(object_file),
                 }
+            }
+
+            // Link the merged object file
+            let object_file = merged_object.ok_or_else(|| {
+                Diagnostic::new(Stage::Link, &output_path, "No input files provided")
+            })?;
+            link_and_write(object_file, &output_path)
+        }
+
+        Commands::Inspect {
+            input,
+            format,
+            symbols_only,
+        } => {
+            let bytes = fs::read(input).map_err(|e| Diagnostic::new(Stage::Read, input, e))?;
+            let kind = inspect::deserialize(&bytes)
+                .map_err(|e| Diagnostic::new(Stage::Deserialize, input, e))?;
+            println!("{}", inspect::render(&kind, *format, *symbols_only));
+            Ok(())
+        }
+
+        Commands::Pack { input, dir, output } => {
+            let archive = if let Some(dir) = dir {
+                archive::Archive::pack_dir(dir).map_err(|e| Diagnostic::new(Stage::Read, dir, e))?
+            } else if input.is_empty() {
+                return Err(Diagnostic::new(
+                    Stage::Read,
+                    output,
+                    "No input files provided; pass files or --dir",
+                ));
             } else {
-                eprintln!("No input files provided");
+                archive::Archive::pack(input)
+                    .map_err(|e| Diagnostic::new(Stage::Read, &input[0], e))?
+            };
+
+            fs::write(output, archive.serialize())
+                .map_err(|e| Diagnostic::new(Stage::Write, output, e))?;
+            println!(
+                "Successfully wrote archive with {} member(s) to {:?}",
+                archive.members.len(),
+                output
+            );
+            Ok(())
+        }
+
+        Commands::Unpack { input, output } => {
+            let bytes = fs::read(input).map_err(|e| Diagnostic::new(Stage::Read, input, e))?;
+            let archive = archive::Archive::deserialize(&bytes)
+                .map_err(|e| Diagnostic::new(Stage::Deserialize, input, e))?;
+
+            fs::create_dir_all(output).map_err(|e| Diagnostic::new(Stage::Write, output, e))?;
+            for member in &archive.members {
+                let member_path = output.join(format!("{}.o", member.name));
+                if let Some(parent) = member_path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| Diagnostic::new(Stage::Write, parent, e))?;
+                }
+                fs::write(&member_path, &member.bytes)
+                    .map_err(|e| Diagnostic::new(Stage::Write, &member_path, e))?;
             }
+            println!(
+                "Successfully unpacked {} member(s) to {:?}",
+                archive.members.len(),
+                output
+            );
+            Ok(())
         }
     }
 }
+
+/// Convert a merged object file into an executable and write it, the
+/// shared tail of `link`/`build`/`compile`'s link step.
+fn link_and_write(object_file: ObjectFile, output_path: &PathBuf) -> Result<(), Diagnostic> {
+    let executable = Executable::try_from(object_file)
+        .map_err(|e| Diagnostic::new(Stage::Link, output_path, format!("{:?}", e)))?;
+    fs::write(output_path, executable.serialize())
+        .map_err(|e| Diagnostic::new(Stage::Write, output_path, e))?;
+    println!("Successfully wrote executable to {:?}", output_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_file_does_not_duplicate_the_assembler_s_line_and_caret() {
+        let path = std::env::temp_dir().join("monistode_assemble_file_test.asm");
+        fs::write(&path, ".text\nmain:\nhalt\nbogus garbage\n").unwrap();
+
+        let err = assemble_file(&path, "stack").unwrap_err();
+        let _ = fs::remove_file(&path);
+
+        let rendered = err.render();
+        assert_eq!(
+            rendered.matches("bogus garbage").count(),
+            1,
+            "offending line should appear once, not once in the message and \
+             once more from with_source/with_span:\n{}",
+            rendered
+        );
+        assert_eq!(
+            rendered.matches('^').count(),
+            1,
+            "caret should appear once, not once in the message and once \
+             more from with_source/with_span:\n{}",
+            rendered
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Project manifests for the `build` subcommand: a declarative description
+//! of which source files assemble into which executables, so multi-file
+//! programs don't need an external shell script to orchestrate them.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A full project manifest, keyed by the names used to refer to sources
+/// and outputs from the command line.
+#[derive(Deserialize)]
+pub struct Manifest {
+    pub sources: HashMap<String, SourceDef>,
+    pub outputs: HashMap<String, OutputDef>,
+}
+
+/// A single source file and the target it should be assembled for.
+#[derive(Deserialize)]
+pub struct SourceDef {
+    pub path: PathBuf,
+    #[serde(default = "default_target")]
+    pub target: String,
+}
+
+fn default_target() -> String {
+    "stack".to_string()
+}
+
+/// A linked executable: the sources that feed into it, where to write it,
+/// and whether it's the one `monistode build` picks with no target name.
+#[derive(Deserialize)]
+pub struct OutputDef {
+    pub sources: Vec<String>,
+    pub output: Option<PathBuf>,
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// Parse a manifest from its on-disk representation. TOML is detected by
+/// the `.toml` extension; anything else is parsed as RON.
+pub fn load(path: &PathBuf) -> Result<Manifest, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+
+    if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+        toml::from_str(&contents).map_err(|e| format!("Failed to parse manifest: {}", e))
+    } else {
+        ron::from_str(&contents).map_err(|e| format!("Failed to parse manifest: {}", e))
+    }
+}
+
+/// Resolve the output to build: the named target if one was given,
+/// otherwise the output marked `default = true`, erroring if neither
+/// uniquely identifies one.
+pub fn resolve_target<'a>(
+    manifest: &'a Manifest,
+    target: Option<&str>,
+) -> Result<(&'a str, &'a OutputDef), String> {
+    match target {
+        Some(name) => manifest
+            .outputs
+            .get_key_value(name)
+            .map(|(name, def)| (name.as_str(), def))
+            .ok_or_else(|| format!("No such build target: {}", name)),
+        None => {
+            let defaults: Vec<_> = manifest
+                .outputs
+                .iter()
+                .filter(|(_, def)| def.default)
+                .collect();
+            match defaults.as_slice() {
+                [(name, def)] => Ok((name.as_str(), def)),
+                [] => Err("No default build target; pass a target name".to_string()),
+                _ => Err("Multiple default build targets declared".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(default: bool) -> OutputDef {
+        OutputDef {
+            sources: Vec::new(),
+            output: None,
+            default,
+        }
+    }
+
+    fn manifest(outputs: HashMap<String, OutputDef>) -> Manifest {
+        Manifest {
+            sources: HashMap::new(),
+            outputs,
+        }
+    }
+
+    #[test]
+    fn resolves_named_target() {
+        let manifest = manifest(HashMap::from([("core".to_string(), output(false))]));
+        let (name, _) = resolve_target(&manifest, Some("core")).unwrap();
+        assert_eq!(name, "core");
+    }
+
+    #[test]
+    fn errors_on_unknown_named_target() {
+        let manifest = manifest(HashMap::from([("core".to_string(), output(false))]));
+        assert!(resolve_target(&manifest, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn resolves_the_sole_default_target_when_none_named() {
+        let manifest = manifest(HashMap::from([
+            ("core".to_string(), output(true)),
+            ("tests".to_string(), output(false)),
+        ]));
+        let (name, _) = resolve_target(&manifest, None).unwrap();
+        assert_eq!(name, "core");
+    }
+
+    #[test]
+    fn errors_when_no_default_target_declared() {
+        let manifest = manifest(HashMap::from([("core".to_string(), output(false))]));
+        assert!(resolve_target(&manifest, None).is_err());
+    }
+
+    #[test]
+    fn errors_when_multiple_default_targets_declared() {
+        let manifest = manifest(HashMap::from([
+            ("core".to_string(), output(true)),
+            ("tests".to_string(), output(true)),
+        ]));
+        assert!(resolve_target(&manifest, None).is_err());
+    }
+}